@@ -0,0 +1,522 @@
+use crate::csr::{Csr, MCAUSE, MEPC, MSTATUS, MSTATUS_MIE, MSTATUS_MPIE, MTVAL, MTVEC};
+use crate::elf::{self, ElfError};
+use crate::exception::Exception;
+use crate::rvc;
+use crate::DRAM_SIZE;
+
+pub struct Cpu {
+    pub(crate) regs: [u64; 32],
+    pub(crate) pc: u64,
+    pub(crate) dram: Vec<u8>,
+    csrs: Csr,
+}
+
+impl Cpu {
+    /// Loads `code` as an ELF64 executable: each `PT_LOAD` segment is
+    /// copied into `dram` at its virtual address (zero-filling the BSS
+    /// tail), and `pc` starts at `e_entry`.
+    pub fn new(code: Vec<u8>) -> Result<Self, ElfError> {
+        let elf = elf::load(&code)?;
+        let mut regs: [u64; 32] = [0; 32];
+        regs[2] = DRAM_SIZE;
+        Ok(Self {
+            regs,
+            pc: elf.entry,
+            dram: elf.dram,
+            csrs: Csr::new(),
+        })
+    }
+
+    /// Loads `code` as a raw flat image placed at address 0 with `pc`
+    /// starting there, bypassing ELF parsing entirely.
+    pub fn from_flat_binary(code: Vec<u8>) -> Self {
+        let mut regs: [u64; 32] = [0; 32];
+        regs[2] = DRAM_SIZE;
+        Self {
+            regs,
+            pc: 0,
+            dram: code,
+            csrs: Csr::new(),
+        }
+    }
+
+    /// Reads the value of `regs[n]`, always returning 0 for `x0` or an
+    /// out-of-range `n`.
+    pub(crate) fn read_reg(&self, n: usize) -> u64 {
+        if n == 0 { 0 } else { self.regs.get(n).copied().unwrap_or(0) }
+    }
+
+    /// Writes `value` into `regs[n]`, discarding writes to `x0` or an
+    /// out-of-range `n`.
+    pub(crate) fn write_reg(&mut self, n: usize, value: u64) {
+        if n != 0 {
+            if let Some(slot) = self.regs.get_mut(n) {
+                *slot = value;
+            }
+        }
+    }
+
+    /// Reads the little-endian 32-bit word at `pc`.
+    pub fn fetch(&self) -> Result<u32, Exception> {
+        let index = self.pc as usize;
+        let bytes = self
+            .dram
+            .get(index..index + 4)
+            .ok_or(Exception::LoadAccessFault(self.pc))?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Reads the little-endian 16-bit halfword at `pc`.
+    fn fetch_halfword(&self) -> Result<u16, Exception> {
+        let index = self.pc as usize;
+        let bytes = self
+            .dram
+            .get(index..index + 2)
+            .ok_or(Exception::LoadAccessFault(self.pc))?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Fetches, decodes, and executes a single instruction, leaving `pc`
+    /// pointing at the next instruction to run.
+    ///
+    /// The low two bits of the first halfword distinguish a standard 32-bit
+    /// instruction (`0b11`) from a 16-bit compressed one, which is expanded
+    /// via [`rvc::decompress`] before reaching the shared execute path. Any
+    /// exception raised along the way is delivered to the machine-mode trap
+    /// handler rather than stopping the CPU. Returns `true` if the
+    /// instruction trapped, so callers that need to regain control (e.g.
+    /// the GDB stub's `continue`) can stop stepping instead of looping
+    /// on a faulting instruction forever.
+    pub fn step(&mut self) -> bool {
+        let decoded = self.fetch_halfword().and_then(|half| {
+            if half & 0b11 == 0b11 {
+                self.fetch().map(|inst| (inst, 4u64))
+            } else {
+                rvc::decompress(half)
+                    .map(|inst| (inst, 2u64))
+                    .ok_or(Exception::IllegalInstruction(half as u64))
+            }
+        });
+
+        match decoded.and_then(|(inst, len)| self.execute(inst, len)) {
+            Ok(next_pc) => {
+                self.pc = next_pc;
+                false
+            }
+            Err(exception) => {
+                self.enter_trap(exception);
+                true
+            }
+        }
+    }
+
+    /// Runs instructions forever, one `step()` at a time.
+    pub fn run(&mut self) {
+        loop {
+            self.step();
+        }
+    }
+
+    /// Delivers `exception` to the machine-mode trap handler: records the
+    /// faulting `pc`, cause, and faulting value, updates `mstatus`, and
+    /// sets `pc` to `mtvec`'s base address.
+    ///
+    /// `mtvec`'s vectored mode (low bits `01`) only applies to asynchronous
+    /// interrupts, never to synchronous exceptions; since every exception
+    /// this CPU raises is synchronous, and it doesn't yet model interrupts,
+    /// traps always land at the base address regardless of mode.
+    fn enter_trap(&mut self, exception: Exception) {
+        let mstatus = self.csrs.read(MSTATUS);
+        let mie = (mstatus & MSTATUS_MIE) != 0;
+        let mut next_mstatus = mstatus & !MSTATUS_MIE & !MSTATUS_MPIE;
+        if mie {
+            next_mstatus |= MSTATUS_MPIE;
+        }
+        self.csrs.write(MSTATUS, next_mstatus);
+
+        self.csrs.write(MEPC, self.pc);
+        self.csrs.write(MCAUSE, exception.cause());
+        self.csrs.write(MTVAL, exception.tval());
+
+        self.pc = self.csrs.read(MTVEC) & !0b11;
+    }
+
+    /// Executes `mret`: restores `mstatus` and returns to `mepc`.
+    fn mret(&mut self) -> u64 {
+        let mstatus = self.csrs.read(MSTATUS);
+        let mpie = (mstatus & MSTATUS_MPIE) != 0;
+        let mut next_mstatus = (mstatus & !MSTATUS_MIE) | MSTATUS_MPIE;
+        if mpie {
+            next_mstatus |= MSTATUS_MIE;
+        }
+        self.csrs.write(MSTATUS, next_mstatus);
+        self.csrs.read(MEPC)
+    }
+
+    /// Decodes and executes `inst` (`len` bytes long), returning the value
+    /// `pc` should take on next (either `pc + len`, a branch/jump target,
+    /// or `mepc` for `mret`).
+    fn execute(&mut self, inst: u32, len: u64) -> Result<u64, Exception> {
+        let opcode = inst & 0x7f;
+        let rd = ((inst >> 7) & 0x1f) as usize;
+        let rs1 = ((inst >> 15) & 0x1f) as usize;
+        let rs2 = ((inst >> 20) & 0x1f) as usize;
+        let funct3 = (inst >> 12) & 0x7;
+        let funct7 = (inst >> 25) & 0x7f;
+
+        match opcode {
+            // LUI
+            0b0110111 => {
+                self.write_reg(rd, imm_u(inst));
+                Ok(self.pc.wrapping_add(len))
+            }
+            // AUIPC
+            0b0010111 => {
+                self.write_reg(rd, self.pc.wrapping_add(imm_u(inst)));
+                Ok(self.pc.wrapping_add(len))
+            }
+            // JAL
+            0b1101111 => {
+                let target = self.pc.wrapping_add(imm_j(inst));
+                self.write_reg(rd, self.pc.wrapping_add(len));
+                Ok(target)
+            }
+            // JALR
+            0b1100111 => {
+                let target = self.read_reg(rs1).wrapping_add(imm_i(inst)) & !1;
+                self.write_reg(rd, self.pc.wrapping_add(len));
+                Ok(target)
+            }
+            // BRANCH
+            0b1100011 => {
+                let taken = match funct3 {
+                    0b000 => self.read_reg(rs1) == self.read_reg(rs2), // beq
+                    0b001 => self.read_reg(rs1) != self.read_reg(rs2), // bne
+                    0b100 => (self.read_reg(rs1) as i64) < (self.read_reg(rs2) as i64), // blt
+                    0b101 => (self.read_reg(rs1) as i64) >= (self.read_reg(rs2) as i64), // bge
+                    0b110 => self.read_reg(rs1) < self.read_reg(rs2), // bltu
+                    0b111 => self.read_reg(rs1) >= self.read_reg(rs2), // bgeu
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                if taken {
+                    Ok(self.pc.wrapping_add(imm_b(inst)))
+                } else {
+                    Ok(self.pc.wrapping_add(len))
+                }
+            }
+            // LOAD
+            0b0000011 => {
+                let addr = self.read_reg(rs1).wrapping_add(imm_i(inst));
+                let value = match funct3 {
+                    0b000 => self.load(addr, 1)? as i8 as i64 as u64,  // lb
+                    0b001 => self.load(addr, 2)? as i16 as i64 as u64, // lh
+                    0b010 => self.load(addr, 4)? as i32 as i64 as u64, // lw
+                    0b011 => self.load(addr, 8)?,                      // ld
+                    0b100 => self.load(addr, 1)?,                      // lbu
+                    0b101 => self.load(addr, 2)?,                      // lhu
+                    0b110 => self.load(addr, 4)?,                      // lwu
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                self.write_reg(rd, value);
+                Ok(self.pc.wrapping_add(len))
+            }
+            // STORE
+            0b0100011 => {
+                let addr = self.read_reg(rs1).wrapping_add(imm_s(inst));
+                let value = self.read_reg(rs2);
+                match funct3 {
+                    0b000 => self.store(addr, 1, value)?, // sb
+                    0b001 => self.store(addr, 2, value)?, // sh
+                    0b010 => self.store(addr, 4, value)?, // sw
+                    0b011 => self.store(addr, 8, value)?, // sd
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                }
+                Ok(self.pc.wrapping_add(len))
+            }
+            // OP-IMM
+            0b0010011 => {
+                let imm = imm_i(inst);
+                let shamt = (inst >> 20) & 0x3f;
+                let value = match funct3 {
+                    0b000 => self.read_reg(rs1).wrapping_add(imm), // addi
+                    0b010 => ((self.read_reg(rs1) as i64) < (imm as i64)) as u64, // slti
+                    0b011 => (self.read_reg(rs1) < imm) as u64,    // sltiu
+                    0b100 => self.read_reg(rs1) ^ imm,             // xori
+                    0b110 => self.read_reg(rs1) | imm,             // ori
+                    0b111 => self.read_reg(rs1) & imm,             // andi
+                    0b001 => self.read_reg(rs1) << shamt,          // slli
+                    0b101 if funct7 & 0b0100000 != 0 => {
+                        ((self.read_reg(rs1) as i64) >> shamt) as u64 // srai
+                    }
+                    0b101 => self.read_reg(rs1) >> shamt, // srli
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                self.write_reg(rd, value);
+                Ok(self.pc.wrapping_add(len))
+            }
+            // OP-IMM-32
+            0b0011011 => {
+                let imm = imm_i(inst);
+                let shamt = (inst >> 20) & 0x1f;
+                let value = match funct3 {
+                    0b000 => (self.read_reg(rs1).wrapping_add(imm) as i32) as i64 as u64, // addiw
+                    0b001 => ((self.read_reg(rs1) as u32) << shamt) as i32 as i64 as u64,  // slliw
+                    0b101 if funct7 & 0b0100000 != 0 => {
+                        ((self.read_reg(rs1) as i32) >> shamt) as i64 as u64 // sraiw
+                    }
+                    0b101 => ((self.read_reg(rs1) as u32) >> shamt) as i32 as i64 as u64, // srliw
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                self.write_reg(rd, value);
+                Ok(self.pc.wrapping_add(len))
+            }
+            // OP
+            0b0110011 => {
+                let value = match (funct3, funct7) {
+                    (0b000, 0b0000000) => self.read_reg(rs1).wrapping_add(self.read_reg(rs2)), // add
+                    (0b000, 0b0100000) => self.read_reg(rs1).wrapping_sub(self.read_reg(rs2)), // sub
+                    (0b001, _) => self.read_reg(rs1) << (self.read_reg(rs2) & 0x3f), // sll
+                    (0b010, _) => ((self.read_reg(rs1) as i64) < (self.read_reg(rs2) as i64)) as u64, // slt
+                    (0b011, _) => (self.read_reg(rs1) < self.read_reg(rs2)) as u64, // sltu
+                    (0b100, _) => self.read_reg(rs1) ^ self.read_reg(rs2), // xor
+                    (0b101, 0b0000000) => self.read_reg(rs1) >> (self.read_reg(rs2) & 0x3f), // srl
+                    (0b101, 0b0100000) => {
+                        ((self.read_reg(rs1) as i64) >> (self.read_reg(rs2) & 0x3f)) as u64 // sra
+                    }
+                    (0b110, _) => self.read_reg(rs1) | self.read_reg(rs2), // or
+                    (0b111, _) => self.read_reg(rs1) & self.read_reg(rs2), // and
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                self.write_reg(rd, value);
+                Ok(self.pc.wrapping_add(len))
+            }
+            // OP-32
+            0b0111011 => {
+                let value = match (funct3, funct7) {
+                    (0b000, 0b0000000) => {
+                        (self.read_reg(rs1).wrapping_add(self.read_reg(rs2)) as i32) as i64 as u64 // addw
+                    }
+                    (0b000, 0b0100000) => {
+                        (self.read_reg(rs1).wrapping_sub(self.read_reg(rs2)) as i32) as i64 as u64 // subw
+                    }
+                    (0b001, _) => {
+                        ((self.read_reg(rs1) as u32) << (self.read_reg(rs2) & 0x1f)) as i32 as i64 as u64
+                        // sllw
+                    }
+                    (0b101, 0b0000000) => {
+                        ((self.read_reg(rs1) as u32) >> (self.read_reg(rs2) & 0x1f)) as i32 as i64 as u64
+                        // srlw
+                    }
+                    (0b101, 0b0100000) => {
+                        ((self.read_reg(rs1) as i32) >> (self.read_reg(rs2) & 0x1f)) as i64 as u64 // sraw
+                    }
+                    _ => return Err(Exception::IllegalInstruction(inst as u64)),
+                };
+                self.write_reg(rd, value);
+                Ok(self.pc.wrapping_add(len))
+            }
+            // SYSTEM
+            0b1110011 => {
+                let csr_addr = (inst >> 20) as u64;
+                match funct3 {
+                    0b000 => match inst >> 20 {
+                        0x000 => Err(Exception::EnvironmentCall), // ecall
+                        0x001 => Err(Exception::Breakpoint),      // ebreak
+                        0x302 => Ok(self.mret()),
+                        _ => Err(Exception::IllegalInstruction(inst as u64)),
+                    },
+                    0b001 => {
+                        // csrrw
+                        let t = self.csrs.read(csr_addr);
+                        self.csrs.write(csr_addr, self.read_reg(rs1));
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    0b010 => {
+                        // csrrs
+                        let t = self.csrs.read(csr_addr);
+                        if rs1 != 0 {
+                            self.csrs.write(csr_addr, t | self.read_reg(rs1));
+                        }
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    0b011 => {
+                        // csrrc
+                        let t = self.csrs.read(csr_addr);
+                        if rs1 != 0 {
+                            self.csrs.write(csr_addr, t & !self.read_reg(rs1));
+                        }
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    0b101 => {
+                        // csrrwi
+                        let uimm = rs1 as u64;
+                        let t = self.csrs.read(csr_addr);
+                        self.csrs.write(csr_addr, uimm);
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    0b110 => {
+                        // csrrsi
+                        let uimm = rs1 as u64;
+                        let t = self.csrs.read(csr_addr);
+                        if uimm != 0 {
+                            self.csrs.write(csr_addr, t | uimm);
+                        }
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    0b111 => {
+                        // csrrci
+                        let uimm = rs1 as u64;
+                        let t = self.csrs.read(csr_addr);
+                        if uimm != 0 {
+                            self.csrs.write(csr_addr, t & !uimm);
+                        }
+                        self.write_reg(rd, t);
+                        Ok(self.pc.wrapping_add(len))
+                    }
+                    _ => Err(Exception::IllegalInstruction(inst as u64)),
+                }
+            }
+            _ => Err(Exception::IllegalInstruction(inst as u64)),
+        }
+    }
+
+    fn load(&self, addr: u64, size: u64) -> Result<u64, Exception> {
+        if !addr.is_multiple_of(size) {
+            return Err(Exception::LoadAddressMisaligned(addr));
+        }
+        let start = addr as usize;
+        let end = start + size as usize;
+        let bytes = self
+            .dram
+            .get(start..end)
+            .ok_or(Exception::LoadAccessFault(addr))?;
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn store(&mut self, addr: u64, size: u64, value: u64) -> Result<(), Exception> {
+        if !addr.is_multiple_of(size) {
+            return Err(Exception::StoreAddressMisaligned(addr));
+        }
+        let start = addr as usize;
+        let end = start + size as usize;
+        let slice = self
+            .dram
+            .get_mut(start..end)
+            .ok_or(Exception::StoreAccessFault(addr))?;
+        slice.copy_from_slice(&value.to_le_bytes()[..size as usize]);
+        Ok(())
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u64 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as i64 as u64
+}
+
+fn imm_i(inst: u32) -> u64 {
+    ((inst as i32 as i64) >> 20) as u64
+}
+
+fn imm_s(inst: u32) -> u64 {
+    let imm115 = (inst >> 25) & 0x7f;
+    let imm40 = (inst >> 7) & 0x1f;
+    sign_extend((imm115 << 5) | imm40, 12)
+}
+
+fn imm_b(inst: u32) -> u64 {
+    let imm12 = (inst >> 31) & 0x1;
+    let imm105 = (inst >> 25) & 0x3f;
+    let imm41 = (inst >> 8) & 0xf;
+    let imm11 = (inst >> 7) & 0x1;
+    sign_extend((imm12 << 12) | (imm11 << 11) | (imm105 << 5) | (imm41 << 1), 13)
+}
+
+fn imm_u(inst: u32) -> u64 {
+    (inst & 0xfffff000) as i32 as i64 as u64
+}
+
+fn imm_j(inst: u32) -> u64 {
+    let imm20 = (inst >> 31) & 0x1;
+    let imm101 = (inst >> 21) & 0x3ff;
+    let imm11 = (inst >> 20) & 0x1;
+    let imm1912 = (inst >> 12) & 0xff;
+    sign_extend(
+        (imm20 << 20) | (imm1912 << 12) | (imm11 << 11) | (imm101 << 1),
+        21,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cpu;
+
+    fn push(code: &mut Vec<u8>, inst: u32) {
+        code.extend_from_slice(&inst.to_le_bytes());
+    }
+
+    // addi rd, rs1, imm
+    fn addi(rd: u32, rs1: u32, imm: u32) -> u32 {
+        ((imm & 0xfff) << 20) | (rs1 << 15) | (rd << 7) | 0b0010011
+    }
+
+    // sw rs2, imm(rs1)
+    fn sw(rs2: u32, rs1: u32, imm: u32) -> u32 {
+        let imm = imm & 0xfff;
+        ((imm >> 5) << 25) | (rs2 << 20) | (rs1 << 15) | (0b010 << 12) | ((imm & 0x1f) << 7) | 0b0100011
+    }
+
+    // lw rd, imm(rs1)
+    fn lw(rd: u32, rs1: u32, imm: u32) -> u32 {
+        ((imm & 0xfff) << 20) | (rs1 << 15) | (0b010 << 12) | (rd << 7) | 0b0000011
+    }
+
+    // beq rs1, rs2, imm (imm relative to this instruction's pc, must be even)
+    fn beq(rs1: u32, rs2: u32, imm: u32) -> u32 {
+        let imm12 = (imm >> 12) & 0x1;
+        let imm11 = (imm >> 11) & 0x1;
+        let imm105 = (imm >> 5) & 0x3f;
+        let imm41 = (imm >> 1) & 0xf;
+        (imm12 << 31) | (imm105 << 25) | (rs2 << 20) | (rs1 << 15) | (imm41 << 8) | (imm11 << 7) | 0b1100011
+    }
+
+    #[test]
+    fn addi_sw_lw_and_taken_branch() {
+        let mut code = Vec::new();
+        push(&mut code, addi(5, 0, 10)); // x5 = 10                         @0
+        push(&mut code, addi(6, 0, 64)); // x6 = 64 (store/load address, past the code) @4
+        push(&mut code, sw(5, 6, 0)); // mem[64] = x5                      @8
+        push(&mut code, lw(7, 6, 0)); // x7 = mem[64]                      @12
+        push(&mut code, beq(5, 7, 8)); // x5 == x7 -> skip next instruction @16
+        push(&mut code, addi(8, 0, 999)); // skipped                        @20
+        push(&mut code, addi(8, 0, 1)); // x8 = 1 (branch target)           @24
+        code.resize(72, 0);
+
+        let mut cpu = Cpu::from_flat_binary(code);
+        for _ in 0..6 {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.pc, 28);
+        assert_eq!(cpu.read_reg(5), 10);
+        assert_eq!(cpu.read_reg(6), 64);
+        assert_eq!(cpu.read_reg(7), 10);
+        assert_eq!(cpu.read_reg(8), 1);
+        assert_eq!(u32::from_le_bytes(cpu.dram[64..68].try_into().unwrap()), 10);
+    }
+
+    #[test]
+    fn x0_reads_as_zero_and_ignores_writes() {
+        let mut code = Vec::new();
+        push(&mut code, addi(0, 0, 5)); // addi x0, x0, 5 (no-op: x0 stays 0)
+        let mut cpu = Cpu::from_flat_binary(code);
+        cpu.step();
+        assert_eq!(cpu.read_reg(0), 0);
+    }
+}
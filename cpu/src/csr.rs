@@ -0,0 +1,34 @@
+//! A flat control/status register (CSR) file.
+//!
+//! Only the machine-mode registers needed for trap delivery are named here;
+//! the rest of the 4096-entry space is addressable but otherwise unused
+//! scratch storage.
+
+pub(crate) const MSTATUS: u64 = 0x300;
+pub(crate) const MTVEC: u64 = 0x305;
+pub(crate) const MEPC: u64 = 0x341;
+pub(crate) const MCAUSE: u64 = 0x342;
+pub(crate) const MTVAL: u64 = 0x343;
+
+/// `mstatus`'s machine-mode interrupt-enable bit.
+pub(crate) const MSTATUS_MIE: u64 = 1 << 3;
+/// `mstatus`'s machine-mode previous interrupt-enable bit.
+pub(crate) const MSTATUS_MPIE: u64 = 1 << 7;
+
+pub(crate) struct Csr {
+    regs: [u64; 4096],
+}
+
+impl Csr {
+    pub(crate) fn new() -> Self {
+        Self { regs: [0; 4096] }
+    }
+
+    pub(crate) fn read(&self, addr: u64) -> u64 {
+        self.regs[addr as usize]
+    }
+
+    pub(crate) fn write(&mut self, addr: u64, value: u64) {
+        self.regs[addr as usize] = value;
+    }
+}
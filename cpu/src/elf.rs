@@ -0,0 +1,197 @@
+//! Minimal ELF64 loader for RISC-V executables.
+//!
+//! Parses just enough of the ELF64 header and program header table to
+//! place each `PT_LOAD` segment into a flat memory image at its virtual
+//! address, mirroring what a bootloader does before jumping to `e_entry`.
+
+use crate::DRAM_SIZE;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const PT_LOAD: u32 = 1;
+
+/// Errors that can occur while parsing an ELF64 image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// The file is too short to hold an ELF64 header.
+    TooShort,
+    /// The `\x7fELF` magic number is missing.
+    BadMagic,
+    /// `e_ident[EI_CLASS]` is not `ELFCLASS64`.
+    Not64Bit,
+    /// `e_ident[EI_DATA]` is not `ELFDATA2LSB`.
+    NotLittleEndian,
+    /// A program header, or a `PT_LOAD` segment's file range, lies
+    /// outside of what the file actually contains.
+    HeaderOutOfBounds,
+    /// A `PT_LOAD` segment's virtual address range does not fit in the
+    /// emulator's fixed-size `dram`, or its `p_filesz` exceeds its
+    /// `p_memsz`.
+    SegmentOutOfBounds,
+}
+
+#[derive(Debug)]
+pub(crate) struct LoadedElf {
+    pub(crate) dram: Vec<u8>,
+    pub(crate) entry: u64,
+}
+
+/// Parses `bytes` as an ELF64 file and lays out its `PT_LOAD` segments
+/// into a `DRAM_SIZE`-sized, zero-filled image.
+pub(crate) fn load(bytes: &[u8]) -> Result<LoadedElf, ElfError> {
+    if bytes.len() < 64 {
+        return Err(ElfError::TooShort);
+    }
+    if bytes[0..4] != ELF_MAGIC {
+        return Err(ElfError::BadMagic);
+    }
+    if bytes[4] != ELFCLASS64 {
+        return Err(ElfError::Not64Bit);
+    }
+    if bytes[5] != ELFDATA2LSB {
+        return Err(ElfError::NotLittleEndian);
+    }
+
+    let entry = read_u64(bytes, 24)?;
+    let phoff = read_u64(bytes, 32)? as usize;
+    let phentsize = read_u16(bytes, 54)? as usize;
+    let phnum = read_u16(bytes, 56)? as usize;
+
+    let mut dram = vec![0u8; DRAM_SIZE as usize];
+    for i in 0..phnum {
+        let header = phoff + i * phentsize;
+        if read_u32(bytes, header)? != PT_LOAD {
+            continue;
+        }
+
+        let p_offset = read_u64(bytes, header + 8)? as usize;
+        let p_vaddr = read_u64(bytes, header + 16)? as usize;
+        let p_filesz = read_u64(bytes, header + 32)? as usize;
+        let p_memsz = read_u64(bytes, header + 40)? as usize;
+
+        if p_filesz > p_memsz {
+            return Err(ElfError::SegmentOutOfBounds);
+        }
+
+        let file_end = p_offset
+            .checked_add(p_filesz)
+            .ok_or(ElfError::HeaderOutOfBounds)?;
+        let segment_bytes = bytes
+            .get(p_offset..file_end)
+            .ok_or(ElfError::HeaderOutOfBounds)?;
+
+        let segment_end = p_vaddr
+            .checked_add(p_memsz)
+            .ok_or(ElfError::SegmentOutOfBounds)?;
+        if segment_end > dram.len() {
+            return Err(ElfError::SegmentOutOfBounds);
+        }
+        dram[p_vaddr..p_vaddr + p_filesz].copy_from_slice(segment_bytes);
+    }
+
+    Ok(LoadedElf { dram, entry })
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, ElfError> {
+    let slice = bytes
+        .get(offset..offset + 2)
+        .ok_or(ElfError::HeaderOutOfBounds)?;
+    Ok(u16::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ElfError> {
+    let slice = bytes
+        .get(offset..offset + 4)
+        .ok_or(ElfError::HeaderOutOfBounds)?;
+    Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, ElfError> {
+    let slice = bytes
+        .get(offset..offset + 8)
+        .ok_or(ElfError::HeaderOutOfBounds)?;
+    Ok(u64::from_le_bytes(slice.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal ELF64 file with a single `PT_LOAD` segment that
+    /// copies `code` to `vaddr` and zero-fills `memsz - code.len()` bytes
+    /// of BSS after it.
+    fn build_elf(entry: u64, vaddr: u64, code: &[u8], memsz: u64) -> Vec<u8> {
+        const EHSIZE: usize = 64;
+        const PHENTSIZE: usize = 56;
+
+        let mut bytes = vec![0u8; EHSIZE];
+        bytes[0..4].copy_from_slice(&ELF_MAGIC);
+        bytes[4] = ELFCLASS64;
+        bytes[5] = ELFDATA2LSB;
+        bytes[24..32].copy_from_slice(&entry.to_le_bytes());
+        bytes[32..40].copy_from_slice(&(EHSIZE as u64).to_le_bytes()); // e_phoff
+        bytes[54..56].copy_from_slice(&(PHENTSIZE as u16).to_le_bytes());
+        bytes[56..58].copy_from_slice(&1u16.to_le_bytes()); // e_phnum
+
+        let mut phdr = vec![0u8; PHENTSIZE];
+        phdr[0..4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        phdr[8..16].copy_from_slice(&(EHSIZE as u64 + PHENTSIZE as u64).to_le_bytes()); // p_offset
+        phdr[16..24].copy_from_slice(&vaddr.to_le_bytes());
+        phdr[32..40].copy_from_slice(&(code.len() as u64).to_le_bytes()); // p_filesz
+        phdr[40..48].copy_from_slice(&memsz.to_le_bytes()); // p_memsz
+        bytes.extend_from_slice(&phdr);
+        bytes.extend_from_slice(code);
+
+        bytes
+    }
+
+    #[test]
+    fn loads_segment_and_zero_fills_bss() {
+        let code = [0x13, 0x00, 0x00, 0x00]; // addi x0, x0, 0
+        let bytes = build_elf(0x1000, 0x1000, &code, 8);
+
+        let loaded = load(&bytes).unwrap();
+        assert_eq!(loaded.entry, 0x1000);
+        assert_eq!(&loaded.dram[0x1000..0x1004], &code);
+        assert_eq!(&loaded.dram[0x1004..0x1008], &[0u8; 4]); // BSS tail
+        assert_eq!(loaded.dram.len(), DRAM_SIZE as usize);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = build_elf(0, 0, &[0u8; 4], 4);
+        bytes[0] = 0;
+        assert_eq!(load(&bytes).unwrap_err(), ElfError::BadMagic);
+    }
+
+    #[test]
+    fn rejects_segment_past_dram_size() {
+        let code = [0u8; 4];
+        let bytes = build_elf(DRAM_SIZE, DRAM_SIZE, &code, 4);
+        assert_eq!(load(&bytes).unwrap_err(), ElfError::SegmentOutOfBounds);
+    }
+
+    #[test]
+    fn rejects_truncated_program_header() {
+        let mut bytes = build_elf(0x1000, 0x1000, &[0u8; 4], 4);
+        bytes.truncate(70); // cuts the program header table short
+        assert_eq!(load(&bytes).unwrap_err(), ElfError::HeaderOutOfBounds);
+    }
+
+    #[test]
+    fn rejects_vaddr_memsz_overflow_without_panicking() {
+        let code = [0u8; 4];
+        let bytes = build_elf(0, u64::MAX - 16, &code, 1024);
+        assert_eq!(load(&bytes).unwrap_err(), ElfError::SegmentOutOfBounds);
+    }
+
+    #[test]
+    fn rejects_filesz_greater_than_memsz() {
+        // filesz > memsz would otherwise pass the dram-fit check (bounded by
+        // memsz) and then panic on the copy, which is bounded by filesz.
+        let code = [0u8; 8];
+        let bytes = build_elf(0x1000, DRAM_SIZE - 4, &code, 4);
+        assert_eq!(load(&bytes).unwrap_err(), ElfError::SegmentOutOfBounds);
+    }
+}
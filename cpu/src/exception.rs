@@ -0,0 +1,50 @@
+/// Conditions raised by the fetch/decode/execute pipeline that require
+/// control to leave normal instruction flow and enter the machine-mode
+/// trap handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    /// The encoding at `pc` was not recognized; carries the raw
+    /// instruction bits (zero-extended when 16-bit compressed).
+    IllegalInstruction(u64),
+    /// A load address, or the `pc` of an instruction fetch, was outside
+    /// of `dram`.
+    LoadAccessFault(u64),
+    /// A store address was outside of `dram`.
+    StoreAccessFault(u64),
+    /// A load address was not aligned to its access size.
+    LoadAddressMisaligned(u64),
+    /// A store address was not aligned to its access size.
+    StoreAddressMisaligned(u64),
+    /// An `ecall` was executed.
+    EnvironmentCall,
+    /// An `ebreak` was executed.
+    Breakpoint,
+}
+
+impl Exception {
+    /// The machine-mode exception code written into `mcause` on trap entry.
+    pub(crate) fn cause(&self) -> u64 {
+        match self {
+            Exception::IllegalInstruction(_) => 2,
+            Exception::Breakpoint => 3,
+            Exception::LoadAddressMisaligned(_) => 4,
+            Exception::LoadAccessFault(_) => 5,
+            Exception::StoreAddressMisaligned(_) => 6,
+            Exception::StoreAccessFault(_) => 7,
+            Exception::EnvironmentCall => 11,
+        }
+    }
+
+    /// The value written into `mtval` on trap entry, or 0 when the
+    /// exception carries none.
+    pub(crate) fn tval(&self) -> u64 {
+        match self {
+            Exception::IllegalInstruction(v)
+            | Exception::LoadAccessFault(v)
+            | Exception::StoreAccessFault(v)
+            | Exception::LoadAddressMisaligned(v)
+            | Exception::StoreAddressMisaligned(v) => *v,
+            Exception::Breakpoint | Exception::EnvironmentCall => 0,
+        }
+    }
+}
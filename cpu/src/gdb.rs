@@ -0,0 +1,263 @@
+//! A minimal GDB Remote Serial Protocol (RSP) server.
+//!
+//! This lets a real `gdb`/`lldb` front end attach over TCP and drive a
+//! [`Cpu`] directly: inspect/modify registers and memory, single-step,
+//! continue, and set software breakpoints. See the GDB documentation on
+//! the "Remote Serial Protocol" for the wire format this implements.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::Cpu;
+
+/// Width in bytes of a general-purpose register as seen by the debugger.
+/// This emulator is RV64-only, so every register (and `pc`) is 8 bytes.
+const REG_BYTES: usize = 8;
+
+/// Number of integer registers (`x0..x31`) GDB expects before `pc`.
+const NUM_INT_REGS: usize = 32;
+
+const TARGET_XML: &str = "<target version=\"1.0\"><architecture>riscv64</architecture></target>";
+
+/// RSP packet size advertised to the client via `qSupported`'s
+/// `PacketSize`; `read_packet` itself does not enforce a length limit.
+const PACKET_SIZE: usize = 4096;
+
+/// A single GDB RSP client connection.
+pub struct GdbServer {
+    stream: TcpStream,
+    breakpoints: Vec<u64>,
+}
+
+impl GdbServer {
+    /// Binds `addr`, blocks until a debugger connects, and returns the
+    /// resulting session.
+    pub fn listen(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            breakpoints: Vec::new(),
+        })
+    }
+
+    /// Serves RSP packets against `cpu` until the debugger disconnects.
+    pub fn run(&mut self, cpu: &mut Cpu) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            self.send_ack()?;
+            if let Some(reply) = self.handle_packet(cpu, &packet) {
+                self.send_packet(&reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_packet(&mut self, cpu: &mut Cpu, packet: &str) -> Option<String> {
+        if packet == "g" {
+            return Some(self.read_all_regs(cpu));
+        }
+        if let Some(data) = packet.strip_prefix('G') {
+            self.write_all_regs(cpu, data);
+            return Some("OK".to_string());
+        }
+        if let Some(rest) = packet.strip_prefix('p') {
+            let n = usize::from_str_radix(rest, 16).ok()?;
+            if n > NUM_INT_REGS {
+                return Some("E01".to_string()); // no such register
+            }
+            return Some(encode_le(self.reg_value(cpu, n)));
+        }
+        if let Some(rest) = packet.strip_prefix('P') {
+            let (n, value) = rest.split_once('=')?;
+            let n = usize::from_str_radix(n, 16).ok()?;
+            let value = decode_le(value)?;
+            if n > NUM_INT_REGS {
+                return Some("E01".to_string()); // no such register
+            }
+            self.set_reg_value(cpu, n, value);
+            return Some("OK".to_string());
+        }
+        if let Some(rest) = packet.strip_prefix('m') {
+            let (addr, len) = rest.split_once(',')?;
+            let addr = u64::from_str_radix(addr, 16).ok()?;
+            let len = usize::from_str_radix(len, 16).ok()?;
+            return Some(self.read_mem(cpu, addr, len));
+        }
+        if let Some(rest) = packet.strip_prefix('M') {
+            let (header, data) = rest.split_once(':')?;
+            let (addr, len) = header.split_once(',')?;
+            let addr = u64::from_str_radix(addr, 16).ok()?;
+            let len = usize::from_str_radix(len, 16).ok()?;
+            self.write_mem(cpu, addr, len, data);
+            return Some("OK".to_string());
+        }
+        if packet == "s" {
+            cpu.step();
+            return Some("S05".to_string());
+        }
+        if packet == "c" {
+            // Runs until a breakpoint is hit or the instruction traps
+            // (illegal instruction, access fault, `ecall`/`ebreak`, ...).
+            // There is no way for a client to interrupt a running `continue`
+            // out-of-band on this transport, so an infinite loop with no
+            // breakpoints and no trap would hang the session forever.
+            loop {
+                let trapped = cpu.step();
+                if trapped || self.breakpoints.contains(&cpu.pc) {
+                    break;
+                }
+            }
+            return Some("S05".to_string());
+        }
+        if let Some(rest) = packet.strip_prefix("Z0,") {
+            let (addr, _kind) = rest.split_once(',')?;
+            let addr = u64::from_str_radix(addr, 16).ok()?;
+            self.breakpoints.push(addr);
+            return Some("OK".to_string());
+        }
+        if let Some(rest) = packet.strip_prefix("z0,") {
+            let (addr, _kind) = rest.split_once(',')?;
+            let addr = u64::from_str_radix(addr, 16).ok()?;
+            self.breakpoints.retain(|&bp| bp != addr);
+            return Some("OK".to_string());
+        }
+        if packet.starts_with("qSupported") {
+            // Advertise qXfer:features:read so the client actually asks for
+            // target.xml instead of assuming a default architecture.
+            return Some(format!("qXfer:features:read+;PacketSize={PACKET_SIZE:x}"));
+        }
+        if packet.starts_with("qXfer:features:read:target.xml:") {
+            return Some(format!("l{TARGET_XML}"));
+        }
+        if packet.starts_with('?') {
+            return Some("S05".to_string());
+        }
+        // Unsupported packet: an empty reply tells GDB the feature isn't implemented.
+        Some(String::new())
+    }
+
+    fn read_all_regs(&self, cpu: &Cpu) -> String {
+        let mut out = String::with_capacity((NUM_INT_REGS + 1) * REG_BYTES * 2);
+        for n in 0..NUM_INT_REGS {
+            out.push_str(&encode_le(cpu.read_reg(n)));
+        }
+        out.push_str(&encode_le(cpu.pc));
+        out
+    }
+
+    fn write_all_regs(&self, cpu: &mut Cpu, data: &str) {
+        let chars_per_reg = REG_BYTES * 2;
+        for n in 0..NUM_INT_REGS {
+            let start = n * chars_per_reg;
+            if let Some(value) = data.get(start..start + chars_per_reg).and_then(decode_le) {
+                cpu.write_reg(n, value);
+            }
+        }
+        let pc_start = NUM_INT_REGS * chars_per_reg;
+        if let Some(value) = data.get(pc_start..pc_start + chars_per_reg).and_then(decode_le) {
+            cpu.pc = value;
+        }
+    }
+
+    /// GDB's risc-v target numbers `x0..x31` as 0..31 and `pc` as 32.
+    fn reg_value(&self, cpu: &Cpu, n: usize) -> u64 {
+        if n == NUM_INT_REGS {
+            cpu.pc
+        } else {
+            cpu.read_reg(n)
+        }
+    }
+
+    fn set_reg_value(&self, cpu: &mut Cpu, n: usize, value: u64) {
+        if n == NUM_INT_REGS {
+            cpu.pc = value;
+        } else {
+            cpu.write_reg(n, value);
+        }
+    }
+
+    fn read_mem(&self, cpu: &Cpu, addr: u64, len: usize) -> String {
+        let start = addr as usize;
+        let end = start.saturating_add(len).min(cpu.dram.len());
+        if start >= cpu.dram.len() {
+            return "E01".to_string();
+        }
+        cpu.dram[start..end]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn write_mem(&self, cpu: &mut Cpu, addr: u64, len: usize, data: &str) {
+        let start = addr as usize;
+        for i in 0..len {
+            let Some(byte_hex) = data.get(i * 2..i * 2 + 2) else {
+                break;
+            };
+            let Ok(byte) = u8::from_str_radix(byte_hex, 16) else {
+                break;
+            };
+            if let Some(slot) = cpu.dram.get_mut(start + i) {
+                *slot = byte;
+            }
+        }
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        let mut byte = [0u8; 1];
+        loop {
+            if self.stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+
+        let mut body = Vec::new();
+        loop {
+            if self.stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'#' {
+                break;
+            }
+            body.push(byte[0]);
+        }
+
+        // Discard the two-byte checksum trailer; this stub trusts the client.
+        let mut checksum = [0u8; 2];
+        self.stream.read_exact(&mut checksum)?;
+
+        Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    fn send_ack(&mut self) -> io::Result<()> {
+        self.stream.write_all(b"+")
+    }
+
+    fn send_packet(&mut self, body: &str) -> io::Result<()> {
+        let checksum = body.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+        write!(self.stream, "${body}#{checksum:02x}")
+    }
+}
+
+fn encode_le(value: u64) -> String {
+    value
+        .to_le_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn decode_le(hex: &str) -> Option<u64> {
+    if hex.len() != REG_BYTES * 2 {
+        return None;
+    }
+    let mut bytes = [0u8; REG_BYTES];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(u64::from_le_bytes(bytes))
+}
@@ -1,19 +1,13 @@
-pub const DRAM_SIZE: u64 = 1024 * 1024 * 128; // 128KB
+pub mod gdb;
 
-struct Cpu {
-    regs: [u64; 32],
-    pc: u64,
-    dram: Vec<u8>,
-}
+mod cpu;
+mod csr;
+mod elf;
+mod exception;
+mod rvc;
 
-impl Cpu {
-    pub fn new(code: Vec<u8>) -> Self {
-        let mut regs: [u64; 32] = [0; 32];
-        regs[2] = DRAM_SIZE;
-        Self {
-            regs,
-            pc: 0,
-            dram: code,
-        }
-    }
-}
+pub const DRAM_SIZE: u64 = 1024 * 1024 * 128; // 128MiB
+
+pub use cpu::Cpu;
+pub use elf::ElfError;
+pub use exception::Exception;
@@ -0,0 +1,402 @@
+//! Decoder for the RISC-V "C" (compressed) 16-bit instruction extension.
+//!
+//! [`decompress`] expands a 16-bit RVC encoding into the equivalent 32-bit
+//! RV64I instruction word so it can be handed to the normal execute path.
+//! This covers the RV64C encodings real `riscv64gc` toolchains emit;
+//! reserved encodings and the handful of quadrant/funct3 slots this
+//! emulator doesn't implement yet report a `None` (illegal instruction).
+
+/// Expands a 16-bit compressed instruction into its RV64I equivalent, or
+/// `None` if `inst` is a reserved/unsupported encoding.
+pub(crate) fn decompress(inst: u16) -> Option<u32> {
+    let quadrant = inst & 0b11;
+    let funct3 = (inst >> 13) & 0b111;
+
+    match (quadrant, funct3) {
+        // C.ADDI4SPN -> addi rd', x2, nzuimm
+        (0b00, 0b000) => {
+            let rd = creg(inst >> 2);
+            let nzuimm = (((inst >> 11) & 0x3) << 4)
+                | (((inst >> 7) & 0xf) << 6)
+                | (((inst >> 6) & 0x1) << 2)
+                | (((inst >> 5) & 0x1) << 3);
+            if nzuimm == 0 {
+                return None; // reserved
+            }
+            Some(encode_i(nzuimm as u32, 2, 0b000, rd, 0b0010011))
+        }
+        // C.LW -> lw rd', offset(rs1')
+        (0b00, 0b010) => {
+            let rd = creg(inst >> 2);
+            let rs1 = creg(inst >> 7);
+            Some(encode_i(word_offset(inst), rs1, 0b010, rd, 0b0000011))
+        }
+        // C.LD -> ld rd', offset(rs1')
+        (0b00, 0b011) => {
+            let rd = creg(inst >> 2);
+            let rs1 = creg(inst >> 7);
+            Some(encode_i(doubleword_offset(inst), rs1, 0b011, rd, 0b0000011))
+        }
+        // C.SW -> sw rs2', offset(rs1')
+        (0b00, 0b110) => {
+            let rs2 = creg(inst >> 2);
+            let rs1 = creg(inst >> 7);
+            Some(encode_s(word_offset(inst), rs2, rs1, 0b010, 0b0100011))
+        }
+        // C.SD -> sd rs2', offset(rs1')
+        (0b00, 0b111) => {
+            let rs2 = creg(inst >> 2);
+            let rs1 = creg(inst >> 7);
+            Some(encode_s(doubleword_offset(inst), rs2, rs1, 0b011, 0b0100011))
+        }
+        // C.ADDI (C.NOP when rd == x0) -> addi rd, rd, imm
+        (0b01, 0b000) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            Some(encode_i(ci_imm(inst) as u32, rd, 0b000, rd, 0b0010011))
+        }
+        // C.ADDIW -> addiw rd, rd, imm (RV64/128 only; C.JAL occupies this
+        // slot in RV32C, but this emulator is RV64-only)
+        (0b01, 0b001) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            if rd == 0 {
+                return None; // reserved
+            }
+            Some(encode_i(ci_imm(inst) as u32, rd, 0b000, rd, 0b0011011))
+        }
+        // C.LI -> addi rd, x0, imm
+        (0b01, 0b010) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            Some(encode_i(ci_imm(inst) as u32, 0, 0b000, rd, 0b0010011))
+        }
+        // C.ADDI16SP (rd == x2) -> addi x2, x2, nzimm
+        // C.LUI (rd != x0, x2) -> lui rd, nzimm
+        (0b01, 0b011) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            if rd == 0 {
+                return None; // reserved
+            }
+            if rd == 2 {
+                let imm = addi16sp_imm(inst);
+                if imm == 0 {
+                    return None; // reserved
+                }
+                Some(encode_i(imm as u32, 2, 0b000, 2, 0b0010011))
+            } else {
+                let imm = lui_imm(inst);
+                if imm == 0 {
+                    return None; // reserved
+                }
+                Some(encode_u(imm, rd, 0b0110111))
+            }
+        }
+        // C.SRLI / C.SRAI / C.ANDI / C.SUB / C.XOR / C.OR / C.AND /
+        // C.SUBW / C.ADDW share this quadrant+funct3 slot.
+        (0b01, 0b100) => {
+            let rd = creg(inst >> 7);
+            match (inst >> 10) & 0x3 {
+                0b00 => {
+                    // C.SRLI -> srli rd', rd', shamt
+                    Some(encode_i(shamt6(inst), rd, 0b101, rd, 0b0010011))
+                }
+                0b01 => {
+                    // C.SRAI -> srai rd', rd', shamt
+                    let imm = shamt6(inst) | (1 << 10);
+                    Some(encode_i(imm, rd, 0b101, rd, 0b0010011))
+                }
+                0b10 => {
+                    // C.ANDI -> andi rd', rd', imm
+                    Some(encode_i(ci_imm(inst) as u32, rd, 0b111, rd, 0b0010011))
+                }
+                _ => {
+                    let rs2 = creg(inst >> 2);
+                    match ((inst >> 12) & 0x1, (inst >> 5) & 0x3) {
+                        (0, 0b00) => Some(encode_r(0b0100000, rs2, rd, 0b000, rd, 0b0110011)), // c.sub
+                        (0, 0b01) => Some(encode_r(0, rs2, rd, 0b100, rd, 0b0110011)), // c.xor
+                        (0, 0b10) => Some(encode_r(0, rs2, rd, 0b110, rd, 0b0110011)), // c.or
+                        (0, 0b11) => Some(encode_r(0, rs2, rd, 0b111, rd, 0b0110011)), // c.and
+                        (1, 0b00) => Some(encode_r(0b0100000, rs2, rd, 0b000, rd, 0b0111011)), // c.subw
+                        (1, 0b01) => Some(encode_r(0, rs2, rd, 0b000, rd, 0b0111011)), // c.addw
+                        _ => None, // reserved
+                    }
+                }
+            }
+        }
+        // C.J -> jal x0, offset
+        (0b01, 0b101) => Some(encode_j(cj_imm(inst) as u32, 0, 0b1101111)),
+        // C.BEQZ -> beq rs1', x0, offset
+        (0b01, 0b110) => {
+            let rs1 = creg(inst >> 7);
+            Some(encode_b(cb_imm(inst) as u32, 0, rs1, 0b000, 0b1100011))
+        }
+        // C.BNEZ -> bne rs1', x0, offset
+        (0b01, 0b111) => {
+            let rs1 = creg(inst >> 7);
+            Some(encode_b(cb_imm(inst) as u32, 0, rs1, 0b001, 0b1100011))
+        }
+        // C.SLLI -> slli rd, rd, shamt
+        (0b10, 0b000) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            Some(encode_i(shamt6(inst), rd, 0b001, rd, 0b0010011))
+        }
+        // C.LWSP -> lw rd, offset(x2)
+        (0b10, 0b010) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            if rd == 0 {
+                return None; // reserved
+            }
+            let offset = (((inst >> 12) & 0x1) << 5)
+                | (((inst >> 4) & 0x7) << 2)
+                | (((inst >> 2) & 0x3) << 6);
+            Some(encode_i(offset as u32, 2, 0b010, rd, 0b0000011))
+        }
+        // C.LDSP -> ld rd, offset(x2)
+        (0b10, 0b011) => {
+            let rd = ((inst >> 7) & 0x1f) as u32;
+            if rd == 0 {
+                return None; // reserved
+            }
+            let offset = (((inst >> 12) & 0x1) << 5)
+                | (((inst >> 5) & 0x3) << 3)
+                | (((inst >> 2) & 0x7) << 6);
+            Some(encode_i(offset as u32, 2, 0b011, rd, 0b0000011))
+        }
+        // C.JR / C.JALR / C.MV / C.ADD share this quadrant+funct3 slot.
+        (0b10, 0b100) => {
+            let rd_rs1 = ((inst >> 7) & 0x1f) as u32;
+            let rs2 = ((inst >> 2) & 0x1f) as u32;
+            let is_jalr_form = (inst >> 12) & 0x1 != 0;
+            match (is_jalr_form, rs2, rd_rs1) {
+                (false, 0, 0) => None, // reserved
+                (false, 0, rs1) => Some(encode_i(0, rs1, 0b000, 0, 0b1100111)), // c.jr
+                (false, rs2, rd) => Some(encode_r(0, rs2, 0, 0b000, rd, 0b0110011)), // c.mv
+                (true, 0, 0) => Some(encode_i(1, 0, 0b000, 0, 0b1110011)), // c.ebreak
+                (true, 0, rs1) => Some(encode_i(0, rs1, 0b000, 1, 0b1100111)), // c.jalr
+                (true, rs2, rd) => Some(encode_r(0, rs2, rd, 0b000, rd, 0b0110011)), // c.add
+            }
+        }
+        // C.SWSP -> sw rs2, offset(x2)
+        (0b10, 0b110) => {
+            let rs2 = ((inst >> 2) & 0x1f) as u32;
+            let offset =
+                (((inst >> 9) & 0xf) << 2) as u32 | (((inst >> 7) & 0x3) as u32) << 6;
+            Some(encode_s(offset, rs2, 2, 0b010, 0b0100011))
+        }
+        // C.SDSP -> sd rs2, offset(x2)
+        (0b10, 0b111) => {
+            let rs2 = ((inst >> 2) & 0x1f) as u32;
+            let offset =
+                (((inst >> 10) & 0x7) << 3) as u32 | (((inst >> 7) & 0x7) as u32) << 6;
+            Some(encode_s(offset, rs2, 2, 0b011, 0b0100011))
+        }
+        _ => None,
+    }
+}
+
+/// Maps a compressed 3-bit register field onto `x8..x15`.
+fn creg(bits: u16) -> u32 {
+    ((bits & 0x7) as u32) + 8
+}
+
+/// Shared `lw`/`sw` (32-bit) offset layout of the CL/CS formats.
+fn word_offset(inst: u16) -> u32 {
+    let i = inst as u32;
+    (((i >> 10) & 0x7) << 3) | (((i >> 6) & 0x1) << 2) | (((i >> 5) & 0x1) << 6)
+}
+
+/// Shared `ld`/`sd` (64-bit) offset layout of the CL/CS formats.
+fn doubleword_offset(inst: u16) -> u32 {
+    let i = inst as u32;
+    (((i >> 10) & 0x7) << 3) | (((i >> 5) & 0x3) << 6)
+}
+
+/// CI-format signed immediate used by `c.addi`/`c.li`.
+fn ci_imm(inst: u16) -> u64 {
+    let i = inst as u32;
+    let imm = (((i >> 12) & 0x1) << 5) | ((i >> 2) & 0x1f);
+    sign_extend(imm, 6)
+}
+
+/// CJ-format signed jump offset used by `c.j`.
+fn cj_imm(inst: u16) -> u64 {
+    let i = inst as u32;
+    let imm11 = (i >> 12) & 0x1;
+    let imm4 = (i >> 11) & 0x1;
+    let imm98 = (i >> 9) & 0x3;
+    let imm10 = (i >> 8) & 0x1;
+    let imm6 = (i >> 7) & 0x1;
+    let imm7 = (i >> 6) & 0x1;
+    let imm31 = (i >> 3) & 0x7;
+    let imm5 = (i >> 2) & 0x1;
+    let imm = (imm11 << 11)
+        | (imm10 << 10)
+        | (imm98 << 8)
+        | (imm7 << 7)
+        | (imm6 << 6)
+        | (imm5 << 5)
+        | (imm4 << 4)
+        | (imm31 << 1);
+    sign_extend(imm, 12)
+}
+
+/// RV64's full 6-bit shift amount, shared by `c.slli`/`c.srli`/`c.srai`.
+fn shamt6(inst: u16) -> u32 {
+    let i = inst as u32;
+    (((i >> 12) & 0x1) << 5) | ((i >> 2) & 0x1f)
+}
+
+/// CI-format `nzimm[17:12]` used by `c.lui`, pre-shifted into a U-type
+/// immediate (i.e. the value's low 12 bits are always zero).
+fn lui_imm(inst: u16) -> u32 {
+    let i = inst as u32;
+    let imm = (((i >> 12) & 0x1) << 5) | ((i >> 2) & 0x1f);
+    (sign_extend(imm, 6) << 12) as u32
+}
+
+/// CI-format `nzimm[9:4]` used by `c.addi16sp`.
+fn addi16sp_imm(inst: u16) -> u64 {
+    let i = inst as u32;
+    let imm9 = (i >> 12) & 0x1;
+    let imm87 = (i >> 3) & 0x3;
+    let imm6 = (i >> 5) & 0x1;
+    let imm5 = (i >> 2) & 0x1;
+    let imm4 = (i >> 6) & 0x1;
+    let imm = (imm9 << 9) | (imm87 << 7) | (imm6 << 6) | (imm5 << 5) | (imm4 << 4);
+    sign_extend(imm, 10)
+}
+
+/// CB-format signed branch offset used by `c.beqz`/`c.bnez`.
+fn cb_imm(inst: u16) -> u64 {
+    let i = inst as u32;
+    let imm8 = (i >> 12) & 0x1;
+    let imm43 = (i >> 10) & 0x3;
+    let imm76 = (i >> 5) & 0x3;
+    let imm21 = (i >> 3) & 0x3;
+    let imm5 = (i >> 2) & 0x1;
+    let imm = (imm8 << 8) | (imm76 << 6) | (imm5 << 5) | (imm43 << 3) | (imm21 << 1);
+    sign_extend(imm, 9)
+}
+
+fn sign_extend(value: u32, bits: u32) -> u64 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as i64 as u64
+}
+
+fn encode_r(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn encode_i(imm: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    ((imm & 0xfff) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn encode_u(imm: u32, rd: u32, opcode: u32) -> u32 {
+    (imm & 0xfffff000) | (rd << 7) | opcode
+}
+
+fn encode_s(imm: u32, rs2: u32, rs1: u32, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm & 0xfff;
+    ((imm >> 5) << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | ((imm & 0x1f) << 7) | opcode
+}
+
+fn encode_b(imm: u32, rs2: u32, rs1: u32, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm & 0x1fff;
+    (((imm >> 12) & 0x1) << 31)
+        | (((imm >> 5) & 0x3f) << 25)
+        | (rs2 << 20)
+        | (rs1 << 15)
+        | (funct3 << 12)
+        | (((imm >> 1) & 0xf) << 8)
+        | (((imm >> 11) & 0x1) << 7)
+        | opcode
+}
+
+fn encode_j(imm: u32, rd: u32, opcode: u32) -> u32 {
+    let imm = imm & 0x1fffff;
+    (((imm >> 20) & 0x1) << 31)
+        | (((imm >> 1) & 0x3ff) << 21)
+        | (((imm >> 11) & 0x1) << 20)
+        | (((imm >> 12) & 0xff) << 12)
+        | (rd << 7)
+        | opcode
+}
+
+#[cfg(test)]
+// The binary literals below are grouped by RVC instruction field (funct3,
+// funct2, rd'/rs1', imm, quadrant, ...) rather than by nibble, so the
+// field layout stays readable at a glance.
+#[allow(clippy::unusual_byte_groupings)]
+mod tests {
+    use super::decompress;
+
+    // c.addiw x8, 5 (quadrant 01, funct3 001, rd'=x8, nzimm=5)
+    #[test]
+    fn addiw_not_jal() {
+        let inst: u16 = 0b001_0_01000_00101_01;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0011011); // OP-IMM-32, not JAL
+        assert_eq!((expanded >> 7) & 0x1f, 8); // rd = x8
+        assert_eq!((expanded >> 15) & 0x1f, 8); // rs1 = x8
+        assert_eq!((expanded as i32) >> 20, 5); // imm = 5
+    }
+
+    #[test]
+    fn addiw_reserved_at_x0() {
+        let inst: u16 = 0b001_0_00000_00101_01;
+        assert_eq!(decompress(inst), None);
+    }
+
+    // c.lui x8, 0x1 (quadrant 01, funct3 011, rd=x8 != x2, nzimm bits = 1)
+    #[test]
+    fn lui() {
+        let inst: u16 = 0b011_0_01000_00001_01;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0110111); // LUI
+        assert_eq!((expanded >> 7) & 0x1f, 8); // rd = x8
+        assert_eq!(expanded & 0xfffff000, 0x1000); // imm[17:12] = 1
+    }
+
+    // c.addi16sp x2, 32 (quadrant 01, funct3 011, rd=x2)
+    #[test]
+    fn addi16sp() {
+        let inst: u16 = 0b011_0_00010_00001_01;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0010011); // ADDI
+        assert_eq!((expanded >> 7) & 0x1f, 2); // rd = x2
+        assert_eq!((expanded >> 15) & 0x1f, 2); // rs1 = x2
+        assert_eq!((expanded as i32) >> 20, 32);
+    }
+
+    // c.slli x8, 3 (quadrant 10, funct3 000)
+    #[test]
+    fn slli() {
+        let inst: u16 = 0b000_0_01000_00011_10;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0010011); // OP-IMM
+        assert_eq!((expanded >> 12) & 0x7, 0b001); // funct3 = slli
+        assert_eq!((expanded >> 20) & 0x3f, 3); // shamt = 3
+    }
+
+    // c.sub x8, x9 (quadrant 01, funct3 100, funct2_hi=11, bit12=0, funct2_lo=00)
+    #[test]
+    fn sub() {
+        let inst: u16 = 0b100_0_11_000_00_001_01;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0110011); // OP
+        assert_eq!((expanded >> 25) & 0x7f, 0b0100000); // funct7 = SUB
+        assert_eq!((expanded >> 7) & 0x1f, 8); // rd = x8
+        assert_eq!((expanded >> 20) & 0x1f, 9); // rs2 = x9
+    }
+
+    // c.sdsp x8, 8(x2) (quadrant 10, funct3 111)
+    #[test]
+    fn sdsp() {
+        let inst: u16 = 0b111_001_000_01000_10;
+        let expanded = decompress(inst).unwrap();
+        assert_eq!(expanded & 0x7f, 0b0100011); // STORE
+        assert_eq!((expanded >> 12) & 0x7, 0b011); // funct3 = sd
+        assert_eq!((expanded >> 15) & 0x1f, 2); // rs1 = x2 (sp)
+        assert_eq!((expanded >> 20) & 0x1f, 8); // rs2 = x8
+        let imm = ((expanded >> 25) << 5) | ((expanded >> 7) & 0x1f);
+        assert_eq!(imm, 8);
+    }
+}